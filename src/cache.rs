@@ -0,0 +1,138 @@
+use std::time::{Duration, Instant};
+
+use cached::{Cached, TimedSizedCache};
+use reqwest::header::HeaderMap;
+use std::sync::Mutex;
+use url::Url;
+
+use crate::Response;
+
+/// Wall-clock-bounded cache of preview `Response`s, keyed by normalized URL.
+///
+/// The outer [`TimedSizedCache`] enforces `capacity` and the configured
+/// `--cache-ttl-secs` as a hard upper bound on entry lifetime; individual
+/// entries may additionally carry a shorter `expires_at` derived from the
+/// upstream `Cache-Control: max-age`, which we check on read.
+pub struct PreviewCache {
+    inner: Mutex<TimedSizedCache<String, CachedResponse>>,
+}
+
+struct CachedResponse {
+    response: Response,
+    expires_at: Instant,
+}
+
+impl PreviewCache {
+    pub fn new(ttl_secs: u64, capacity: usize) -> Self {
+        Self {
+            inner: Mutex::new(TimedSizedCache::with_size_and_lifespan(capacity, ttl_secs)),
+        }
+    }
+
+    /// Returns a cached response for `key`, unless its upstream-derived TTL
+    /// has already elapsed (even if the outer cache would still serve it).
+    pub fn get(&self, key: &str) -> Option<Response> {
+        let mut inner = self.inner.lock().unwrap();
+        let cached = inner.cache_get(&key.to_string())?;
+        if Instant::now() >= cached.expires_at {
+            return None;
+        }
+        Some(cached.response.clone())
+    }
+
+    pub fn insert(&self, key: String, response: Response, ttl: Duration) {
+        let cached = CachedResponse {
+            response,
+            expires_at: Instant::now() + ttl,
+        };
+        self.inner.lock().unwrap().cache_set(key, cached);
+    }
+}
+
+/// Normalizes a URL for use as a cache key: lowercases scheme/host and drops
+/// the fragment, since it never affects the fetched representation. Path and
+/// query are left untouched, since most servers treat those case-sensitively.
+pub fn normalize_url(url: &Url) -> String {
+    let mut normalized = url.clone();
+    normalized.set_fragment(None);
+
+    let lowercase_scheme = normalized.scheme().to_ascii_lowercase();
+    normalized
+        .set_scheme(&lowercase_scheme)
+        .expect("lowercasing a scheme keeps it valid");
+
+    if let Some(host) = normalized.host_str() {
+        let lowercase_host = host.to_ascii_lowercase();
+        normalized
+            .set_host(Some(&lowercase_host))
+            .expect("lowercasing a host keeps it valid");
+    }
+
+    normalized.to_string()
+}
+
+/// Parses `Cache-Control: max-age=N` (or `s-maxage=N`) from upstream response
+/// headers, returning `None` when absent, unparsable, or `no-store`.
+pub fn max_age_from_headers(headers: &HeaderMap) -> Option<Duration> {
+    let value = headers.get(reqwest::header::CACHE_CONTROL)?.to_str().ok()?;
+    if value
+        .split(',')
+        .any(|part| part.trim().eq_ignore_ascii_case("no-store"))
+    {
+        return None;
+    }
+    value.split(',').find_map(|part| {
+        let part = part.trim();
+        let (directive, seconds) = part.split_once('=')?;
+        if directive.trim().eq_ignore_ascii_case("max-age")
+            || directive.trim().eq_ignore_ascii_case("s-maxage")
+        {
+            seconds.trim().parse::<u64>().ok().map(Duration::from_secs)
+        } else {
+            None
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalize_url_lowercases_scheme_and_host_only() {
+        let url = Url::parse("HTTP://Example.COM/Article?Title=Foo#section").unwrap();
+        assert_eq!(normalize_url(&url), "http://example.com/Article?Title=Foo");
+    }
+
+    #[test]
+    fn normalize_url_distinguishes_case_sensitive_paths() {
+        let a = Url::parse("https://example.com/Article").unwrap();
+        let b = Url::parse("https://example.com/article").unwrap();
+        assert_ne!(normalize_url(&a), normalize_url(&b));
+    }
+
+    #[test]
+    fn max_age_parses_first_matching_directive() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            reqwest::header::CACHE_CONTROL,
+            "public, max-age=120".parse().unwrap(),
+        );
+        assert_eq!(max_age_from_headers(&headers), Some(Duration::from_secs(120)));
+    }
+
+    #[test]
+    fn max_age_respects_no_store() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            reqwest::header::CACHE_CONTROL,
+            "no-store, max-age=120".parse().unwrap(),
+        );
+        assert_eq!(max_age_from_headers(&headers), None);
+    }
+
+    #[test]
+    fn max_age_absent_when_header_missing() {
+        assert_eq!(max_age_from_headers(&HeaderMap::new()), None);
+    }
+}
@@ -0,0 +1,130 @@
+use encoding_rs::Encoding;
+use reqwest::header::{HeaderMap, CONTENT_TYPE};
+
+/// Whether `headers` describe a payload worth handing to the HTML parser at
+/// all. A missing `Content-Type` is treated as HTML, matching how browsers
+/// sniff it; anything explicitly declared as something else (image, PDF,
+/// JSON, ...) is not.
+pub fn is_html_content_type(headers: &HeaderMap) -> bool {
+    let Some(value) = headers.get(CONTENT_TYPE).and_then(|v| v.to_str().ok()) else {
+        return true;
+    };
+    let mime = value
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase();
+    mime.is_empty() || mime == "text/html" || mime == "application/xhtml+xml"
+}
+
+/// Decodes `bytes` into UTF-8, picking a source encoding via [`resolve_encoding`].
+pub fn decode(bytes: &[u8], headers: &HeaderMap) -> String {
+    let (decoded, _, _) = resolve_encoding(bytes, headers).decode(bytes);
+    decoded.into_owned()
+}
+
+/// Picks a source encoding from, in order: the `Content-Type` header's
+/// `charset`, a `<meta charset>`/`<meta http-equiv>` tag found by a raw-byte
+/// prescan, and finally statistical detection.
+fn resolve_encoding(bytes: &[u8], headers: &HeaderMap) -> &'static Encoding {
+    charset_from_content_type(headers)
+        .or_else(|| charset_from_meta_tag(bytes))
+        .unwrap_or_else(|| detect_statistically(bytes))
+}
+
+fn charset_from_content_type(headers: &HeaderMap) -> Option<&'static Encoding> {
+    let value = headers.get(CONTENT_TYPE)?.to_str().ok()?;
+    value.split(';').skip(1).find_map(|param| {
+        let (name, label) = param.trim().split_once('=')?;
+        if !name.trim().eq_ignore_ascii_case("charset") {
+            return None;
+        }
+        Encoding::for_label(label.trim().trim_matches('"').as_bytes())
+    })
+}
+
+/// Scans raw, not-yet-decoded bytes for `<meta charset="...">` or
+/// `<meta http-equiv="Content-Type" content="...;charset=...">`, since we
+/// don't know the real encoding yet and can't safely decode to UTF-8 first.
+fn charset_from_meta_tag(bytes: &[u8]) -> Option<&'static Encoding> {
+    let prescan = String::from_utf8_lossy(bytes).to_ascii_lowercase();
+    let charset_pos = prescan.find("charset=")?;
+    let label: String = prescan[charset_pos + "charset=".len()..]
+        .trim_start_matches(['"', '\''])
+        .chars()
+        .take_while(|c| c.is_ascii_alphanumeric() || *c == '-' || *c == '_')
+        .collect();
+    Encoding::for_label(label.as_bytes())
+}
+
+fn detect_statistically(bytes: &[u8]) -> &'static Encoding {
+    let mut detector = chardetng::EncodingDetector::new();
+    detector.feed(bytes, true);
+    detector.guess(None, true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers_with_content_type(value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(CONTENT_TYPE, value.parse().unwrap());
+        headers
+    }
+
+    #[test]
+    fn html_when_content_type_missing() {
+        assert!(is_html_content_type(&HeaderMap::new()));
+    }
+
+    #[test]
+    fn html_for_text_html() {
+        assert!(is_html_content_type(&headers_with_content_type(
+            "text/html; charset=utf-8"
+        )));
+    }
+
+    #[test]
+    fn not_html_for_image() {
+        assert!(!is_html_content_type(&headers_with_content_type("image/png")));
+    }
+
+    #[test]
+    fn content_type_charset_is_highest_priority() {
+        let headers = headers_with_content_type("text/html; charset=shift_jis");
+        let bytes = b"<meta charset=\"utf-8\">";
+        assert_eq!(
+            resolve_encoding(bytes, &headers).name(),
+            encoding_rs::SHIFT_JIS.name()
+        );
+    }
+
+    #[test]
+    fn meta_tag_is_used_when_header_has_no_charset() {
+        let headers = headers_with_content_type("text/html");
+        let bytes = b"<html><head><meta charset=\"gbk\"></head></html>";
+        assert_eq!(
+            resolve_encoding(bytes, &headers).name(),
+            encoding_rs::GBK.name()
+        );
+    }
+
+    #[test]
+    fn meta_http_equiv_charset_is_found() {
+        let bytes = b"<meta http-equiv=\"Content-Type\" content=\"text/html; charset=iso-8859-1\">";
+        assert_eq!(
+            charset_from_meta_tag(bytes).map(Encoding::name),
+            Some(encoding_rs::WINDOWS_1252.name())
+        );
+    }
+
+    #[test]
+    fn falls_back_to_statistical_detection_when_nothing_declared() {
+        let headers = HeaderMap::new();
+        let bytes = "<html><head><title>hello world</title></head></html>".as_bytes();
+        // Plain ASCII should be confidently detected as (or compatible with) UTF-8.
+        assert_eq!(resolve_encoding(bytes, &headers).name(), encoding_rs::UTF_8.name());
+    }
+}
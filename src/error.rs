@@ -0,0 +1,90 @@
+use actix_web::{http::StatusCode, HttpResponse, ResponseError};
+use serde::Serialize;
+use thiserror::Error;
+
+/// Everything that can go wrong while building a preview, mapped to an HTTP
+/// status a client can act on instead of a dropped connection.
+#[derive(Error, Debug)]
+pub enum PreviewError {
+    #[error("failed to reach upstream: {0}")]
+    UpstreamRequest(#[from] reqwest_middleware::Error),
+
+    #[error("upstream responded with status {0}")]
+    UpstreamStatus(reqwest::StatusCode),
+
+    #[error("failed to read upstream response body: {0}")]
+    BodyRead(#[from] reqwest::Error),
+
+    #[error("failed to parse upstream HTML")]
+    MalformedHtml,
+
+    #[error("request header `User-Agent` contains characters other than visible ASCII")]
+    InvalidUserAgent,
+
+    #[error("upstream content type is not HTML")]
+    UnsupportedContentType,
+
+    #[error("refusing to fetch a private or internal address")]
+    DisallowedHost,
+}
+
+#[derive(Serialize)]
+struct ErrorBody {
+    error: String,
+}
+
+impl ResponseError for PreviewError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            PreviewError::UpstreamRequest(_) => StatusCode::BAD_GATEWAY,
+            PreviewError::UpstreamStatus(status) => {
+                StatusCode::from_u16(status.as_u16()).unwrap_or(StatusCode::BAD_GATEWAY)
+            }
+            PreviewError::BodyRead(_) => StatusCode::BAD_GATEWAY,
+            PreviewError::MalformedHtml => StatusCode::BAD_GATEWAY,
+            PreviewError::InvalidUserAgent => StatusCode::BAD_REQUEST,
+            PreviewError::UnsupportedContentType => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            PreviewError::DisallowedHost => StatusCode::BAD_REQUEST,
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        HttpResponse::build(self.status_code()).json(ErrorBody {
+            error: self.to_string(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn upstream_status_passes_through_when_valid() {
+        let err = PreviewError::UpstreamStatus(reqwest::StatusCode::NOT_FOUND);
+        assert_eq!(err.status_code(), StatusCode::NOT_FOUND);
+    }
+
+    #[test]
+    fn malformed_html_maps_to_bad_gateway() {
+        assert_eq!(PreviewError::MalformedHtml.status_code(), StatusCode::BAD_GATEWAY);
+    }
+
+    #[test]
+    fn invalid_user_agent_maps_to_bad_request() {
+        assert_eq!(PreviewError::InvalidUserAgent.status_code(), StatusCode::BAD_REQUEST);
+    }
+
+    #[test]
+    fn unsupported_content_type_maps_to_unsupported_media_type() {
+        assert_eq!(
+            PreviewError::UnsupportedContentType.status_code(),
+            StatusCode::UNSUPPORTED_MEDIA_TYPE
+        );
+    }
+
+    #[test]
+    fn disallowed_host_maps_to_bad_request() {
+        assert_eq!(PreviewError::DisallowedHost.status_code(), StatusCode::BAD_REQUEST);
+    }
+}
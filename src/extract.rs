@@ -0,0 +1,286 @@
+use reqwest_middleware::ClientWithMiddleware;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tl::{Node, VDom};
+use url::Url;
+
+/// `@type`s we're willing to pull title/description/image out of from a
+/// JSON-LD block. Anything else (e.g. `BreadcrumbList`, `Organization`) is
+/// usually not a useful stand-in for OpenGraph/Twitter Card data.
+const JSON_LD_TYPES: [&str; 3] = ["Article", "WebPage", "Product"];
+
+fn attr_from_first_query_match(dom: &VDom, query: &str, attr: &str) -> Option<String> {
+    let query = dom.query_selector(query);
+    let node = query?.next()?.get(dom.parser())?;
+    if let Node::Tag(tag) = node {
+        let content = tag.attributes().get(attr)?;
+        let value = String::from(content?.as_utf8_str());
+        return Some(value);
+    }
+    None
+}
+
+fn get_absolute_path(url: &Url, relative_path: String) -> Option<Url> {
+    url.join(&relative_path).ok()
+}
+
+fn title_from_title_tag(dom: &VDom) -> Option<String> {
+    let query = dom.query_selector("title");
+    let node = query?.next()?.get(dom.parser())?;
+    if let Node::Tag(tag) = node {
+        let title = String::from(tag.inner_text(dom.parser()));
+        return Some(title);
+    }
+    None
+}
+
+fn json_ld_candidates(dom: &VDom) -> Vec<Value> {
+    let Some(query) = dom.query_selector("script[type='application/ld+json']") else {
+        return Vec::new();
+    };
+    let mut candidates = Vec::new();
+    for handle in query {
+        let Some(Node::Tag(tag)) = handle.get(dom.parser()) else {
+            continue;
+        };
+        let raw = tag.inner_text(dom.parser());
+        if let Ok(value) = serde_json::from_str::<Value>(&raw) {
+            flatten_json_ld(value, &mut candidates);
+        }
+    }
+    candidates
+}
+
+/// JSON-LD often nests the actual entities inside a top-level `@graph` array,
+/// so we need to recurse to find the `Article`/`WebPage`/`Product` objects.
+fn flatten_json_ld(value: Value, out: &mut Vec<Value>) {
+    match value {
+        Value::Array(items) => {
+            for item in items {
+                flatten_json_ld(item, out);
+            }
+        }
+        Value::Object(ref map) => {
+            if let Some(graph) = map.get("@graph").cloned() {
+                flatten_json_ld(graph, out);
+            }
+            if is_relevant_json_ld_type(&value) {
+                out.push(value);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn is_relevant_json_ld_type(value: &Value) -> bool {
+    match value.get("@type") {
+        Some(Value::String(type_name)) => JSON_LD_TYPES
+            .iter()
+            .any(|candidate| candidate.eq_ignore_ascii_case(type_name)),
+        Some(Value::Array(type_names)) => type_names.iter().any(|type_name| {
+            type_name
+                .as_str()
+                .is_some_and(|type_name| JSON_LD_TYPES.iter().any(|c| c.eq_ignore_ascii_case(type_name)))
+        }),
+        _ => false,
+    }
+}
+
+fn json_ld_string(dom: &VDom, fields: &[&str]) -> Option<String> {
+    json_ld_candidates(dom).into_iter().find_map(|candidate| {
+        fields
+            .iter()
+            .find_map(|field| candidate.get(*field).and_then(Value::as_str).map(String::from))
+    })
+}
+
+fn json_ld_image(dom: &VDom) -> Option<String> {
+    json_ld_candidates(dom).into_iter().find_map(|candidate| {
+        match candidate.get("image") {
+            Some(Value::String(url)) => Some(url.clone()),
+            Some(Value::Object(image)) => image.get("url").and_then(Value::as_str).map(String::from),
+            Some(Value::Array(images)) => images.first().and_then(|image| match image {
+                Value::String(url) => Some(url.clone()),
+                Value::Object(image) => image.get("url").and_then(Value::as_str).map(String::from),
+                _ => None,
+            }),
+            _ => None,
+        }
+    })
+}
+
+/// Resolves a title by trying, in order: OpenGraph, Twitter Card, JSON-LD
+/// (`headline`/`name`), then the `<title>` tag.
+pub fn get_title(dom: &VDom) -> Option<String> {
+    attr_from_first_query_match(dom, "meta[property='og:title']", "content")
+        .or_else(|| attr_from_first_query_match(dom, "meta[name='twitter:title']", "content"))
+        .or_else(|| json_ld_string(dom, &["headline", "name"]))
+        .or_else(|| title_from_title_tag(dom))
+}
+
+/// Resolves a description by trying, in order: OpenGraph, Twitter Card,
+/// JSON-LD, then the first `<meta name="description">`.
+pub fn get_description(dom: &VDom) -> Option<String> {
+    attr_from_first_query_match(dom, "meta[property='og:description']", "content")
+        .or_else(|| attr_from_first_query_match(dom, "meta[name='twitter:description']", "content"))
+        .or_else(|| json_ld_string(dom, &["description"]))
+        .or_else(|| attr_from_first_query_match(dom, "meta[name='description']", "content"))
+}
+
+/// Resolves an image by trying, in order: OpenGraph, Twitter Card, JSON-LD.
+pub fn get_image(dom: &VDom, url: &Url) -> Option<Url> {
+    attr_from_first_query_match(dom, "meta[property='og:image']", "content")
+        .or_else(|| attr_from_first_query_match(dom, "meta[name='twitter:image']", "content"))
+        .or_else(|| json_ld_image(dom))
+        .and_then(|relative_path| get_absolute_path(url, relative_path))
+}
+
+pub fn get_domain(url: &Url) -> Option<String> {
+    let host = String::from(url.host_str()?);
+    Some(host.replace("www.", ""))
+}
+
+pub fn get_favicon(dom: &VDom, url: &Url) -> Option<Url> {
+    let favicon = attr_from_first_query_match(dom, "link[rel='icon']", "href")?;
+    get_absolute_path(url, favicon)
+}
+
+pub fn get_og_url(dom: &VDom) -> Option<String> {
+    attr_from_first_query_match(dom, "meta[property='og:url']", "content")
+}
+
+pub fn get_sitename(dom: &VDom) -> Option<String> {
+    attr_from_first_query_match(dom, "meta[property='og:site_name']", "content")
+}
+
+pub fn get_type(dom: &VDom) -> Option<String> {
+    attr_from_first_query_match(dom, "meta[property='og:type']", "content")
+}
+
+/// A subset of the oEmbed response fields (<https://oembed.com/>) worth
+/// surfacing alongside our own extracted metadata.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct OEmbed {
+    pub title: Option<String>,
+    pub thumbnail_url: Option<Url>,
+    pub author_name: Option<String>,
+    pub html: Option<String>,
+}
+
+/// Finds `<link rel="alternate" type="application/json+oembed">`, the
+/// standard way a page advertises its oEmbed discovery endpoint.
+pub fn get_oembed_url(dom: &VDom, url: &Url) -> Option<Url> {
+    let href = attr_from_first_query_match(
+        dom,
+        "link[rel='alternate'][type='application/json+oembed']",
+        "href",
+    )?;
+    get_absolute_path(url, href)
+}
+
+/// Fetches and parses an oEmbed endpoint, swallowing failures: oEmbed is a
+/// nice-to-have enrichment, not something that should fail the whole preview.
+pub async fn fetch_oembed(client: &ClientWithMiddleware, url: Url) -> Option<OEmbed> {
+    let response = client.get(url).send().await.ok()?;
+    if !response.status().is_success() {
+        return None;
+    }
+    response.json::<OEmbed>().await.ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dom_for(html: &str) -> VDom {
+        tl::parse(html, tl::ParserOptions::default()).unwrap()
+    }
+
+    #[test]
+    fn flatten_json_ld_keeps_top_level_relevant_object() {
+        let value: Value = serde_json::from_str(r#"{"@type": "Article", "headline": "hi"}"#).unwrap();
+        let mut out = Vec::new();
+        flatten_json_ld(value, &mut out);
+        assert_eq!(out.len(), 1);
+        assert_eq!(out[0]["headline"], "hi");
+    }
+
+    #[test]
+    fn flatten_json_ld_recurses_into_graph() {
+        let value: Value = serde_json::from_str(
+            r#"{"@graph": [{"@type": "Organization"}, {"@type": "WebPage", "name": "Home"}]}"#,
+        )
+        .unwrap();
+        let mut out = Vec::new();
+        flatten_json_ld(value, &mut out);
+        assert_eq!(out.len(), 1);
+        assert_eq!(out[0]["name"], "Home");
+    }
+
+    #[test]
+    fn flatten_json_ld_recurses_into_arrays() {
+        let value: Value = serde_json::from_str(r#"[{"@type": "Product", "name": "Widget"}]"#).unwrap();
+        let mut out = Vec::new();
+        flatten_json_ld(value, &mut out);
+        assert_eq!(out.len(), 1);
+        assert_eq!(out[0]["name"], "Widget");
+    }
+
+    #[test]
+    fn is_relevant_json_ld_type_matches_case_insensitively() {
+        let value: Value = serde_json::from_str(r#"{"@type": "article"}"#).unwrap();
+        assert!(is_relevant_json_ld_type(&value));
+    }
+
+    #[test]
+    fn is_relevant_json_ld_type_matches_any_entry_in_array() {
+        let value: Value = serde_json::from_str(r#"{"@type": ["Thing", "Product"]}"#).unwrap();
+        assert!(is_relevant_json_ld_type(&value));
+    }
+
+    #[test]
+    fn is_relevant_json_ld_type_rejects_unlisted_type() {
+        let value: Value = serde_json::from_str(r#"{"@type": "BreadcrumbList"}"#).unwrap();
+        assert!(!is_relevant_json_ld_type(&value));
+    }
+
+    #[test]
+    fn json_ld_image_reads_plain_string() {
+        let dom = dom_for(
+            r#"<html><head><script type="application/ld+json">
+            {"@type": "Article", "image": "https://example.com/a.png"}
+            </script></head></html>"#,
+        );
+        assert_eq!(json_ld_image(&dom).as_deref(), Some("https://example.com/a.png"));
+    }
+
+    #[test]
+    fn json_ld_image_reads_object_with_url_field() {
+        let dom = dom_for(
+            r#"<html><head><script type="application/ld+json">
+            {"@type": "Article", "image": {"url": "https://example.com/b.png"}}
+            </script></head></html>"#,
+        );
+        assert_eq!(json_ld_image(&dom).as_deref(), Some("https://example.com/b.png"));
+    }
+
+    #[test]
+    fn json_ld_image_reads_first_entry_of_array() {
+        let dom = dom_for(
+            r#"<html><head><script type="application/ld+json">
+            {"@type": "Article", "image": ["https://example.com/c.png", "https://example.com/d.png"]}
+            </script></head></html>"#,
+        );
+        assert_eq!(json_ld_image(&dom).as_deref(), Some("https://example.com/c.png"));
+    }
+
+    #[test]
+    fn json_ld_image_absent_when_no_relevant_candidate() {
+        let dom = dom_for(
+            r#"<html><head><script type="application/ld+json">
+            {"@type": "BreadcrumbList", "image": "https://example.com/ignored.png"}
+            </script></head></html>"#,
+        );
+        assert_eq!(json_ld_image(&dom), None);
+    }
+}
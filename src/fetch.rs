@@ -0,0 +1,79 @@
+use futures_util::StreamExt;
+use reqwest::Response;
+
+const HEAD_CLOSE_TAG: &str = "</head>";
+
+/// Bytes kept from the tail of the previous chunk when scanning for
+/// `</head>`, so the tag is still found if it straddles a chunk boundary.
+const OVERLAP_WINDOW: usize = HEAD_CLOSE_TAG.len() - 1;
+
+/// Streams `response`'s body, stopping as soon as a case-insensitive
+/// `</head>` has been seen or `max_bytes` have been buffered, whichever
+/// comes first. All the metadata this crate extracts lives in `<head>`, so
+/// there's no need to download (or even request) the rest of the page.
+pub async fn read_head(response: Response, max_bytes: usize) -> Result<Vec<u8>, reqwest::Error> {
+    let mut buffer = Vec::new();
+    let mut stream = response.bytes_stream();
+
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk?;
+        buffer.extend_from_slice(&chunk);
+
+        let scan_from = buffer.len().saturating_sub(chunk.len() + OVERLAP_WINDOW);
+        if contains_head_close(&buffer[scan_from..]) {
+            break;
+        }
+        if buffer.len() >= max_bytes {
+            buffer.truncate(max_bytes);
+            break;
+        }
+    }
+
+    Ok(buffer)
+}
+
+fn contains_head_close(haystack: &[u8]) -> bool {
+    haystack
+        .to_ascii_lowercase()
+        .windows(HEAD_CLOSE_TAG.len())
+        .any(|window| window == HEAD_CLOSE_TAG.as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_head_close_tag() {
+        assert!(contains_head_close(b"<head><title>x</title></head>"));
+    }
+
+    #[test]
+    fn is_case_insensitive() {
+        assert!(contains_head_close(b"</HEAD>"));
+        assert!(contains_head_close(b"</Head>"));
+    }
+
+    #[test]
+    fn absent_tag_is_not_found() {
+        assert!(!contains_head_close(b"<head><title>x</title>"));
+    }
+
+    #[test]
+    fn overlap_window_catches_tag_split_across_chunks() {
+        // Mirrors read_head's bookkeeping: the closing tag arrives as
+        // "</hea" at the end of one chunk and "d>" at the start of the next,
+        // so a scan of the last chunk alone would miss it.
+        let mut buffer = b"<head><title>x</title></hea".to_vec();
+        let chunk = b"d>".to_vec();
+        buffer.extend_from_slice(&chunk);
+
+        let scan_from = buffer.len().saturating_sub(chunk.len() + OVERLAP_WINDOW);
+        assert!(contains_head_close(&buffer[scan_from..]));
+    }
+
+    #[test]
+    fn overlap_window_is_one_less_than_tag_length() {
+        assert_eq!(OVERLAP_WINDOW, HEAD_CLOSE_TAG.len() - 1);
+    }
+}
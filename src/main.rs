@@ -1,87 +1,55 @@
+use std::sync::Arc;
+use std::time::Duration;
+
 use actix_cors::Cors;
-use actix_web::{get, web, App, HttpRequest, HttpServer, Responder};
+use actix_web::{get, http::header::CACHE_CONTROL, web, App, HttpRequest, HttpServer, Responder};
 use clap::Parser;
 use reqwest_middleware::ClientBuilder;
 use reqwest_tracing::TracingMiddleware;
 use serde::{Deserialize, Serialize};
-use tl::{Node, VDom};
 use tracing::info;
 use tracing_actix_web::TracingLogger;
-use tracing_subscriber::EnvFilter;
 use url::Url;
 
+mod cache;
+mod decode;
+mod error;
+mod extract;
+mod fetch;
+mod proxy;
+mod security;
+mod telemetry;
+
+use cache::PreviewCache;
+use error::PreviewError;
+use extract::OEmbed;
+
 static APP_USER_AGENT: &str =
     "Mozilla/5.0 (X11; Linux i686; rv:112.0) Gecko/20100101 Firefox/112.0";
 
 #[derive(Deserialize, Debug, Clone)]
 struct Params {
+    #[serde(deserialize_with = "deserialize_http_url")]
     url: Url,
 }
 
-fn attr_from_first_query_match(dom: &VDom, query: &str, attr: &str) -> Option<String> {
-    let query = dom.query_selector(query);
-    let node = query?.next()?.get(dom.parser())?;
-    if let Node::Tag(tag) = node {
-        let content = tag.attributes().get(attr)?;
-        let title = String::from(content?.as_utf8_str());
-        return Some(title);
-    }
-    None
-}
-
-fn get_absolute_path(url: &Url, relative_path: String) -> Option<Url> {
-    url.join(&relative_path).ok()
-}
-
-fn title_from_title_tag(dom: &VDom) -> Option<String> {
-    let query = dom.query_selector("title");
-    let node = query?.next()?.get(dom.parser())?;
-    if let Node::Tag(tag) = node {
-        let title = String::from(tag.inner_text(dom.parser()));
-        return Some(title);
-    }
-    None
-}
-
-fn get_title(dom: &VDom) -> Option<String> {
-    if let Some(title) = attr_from_first_query_match(dom, "meta[property='og:title']", "content") {
-        return Some(title);
+/// Rejects anything but `http`/`https` up front, before the URL ever reaches
+/// the fetch client (e.g. `file://`, `ftp://`, or `gopher://` targets).
+fn deserialize_http_url<'de, D>(deserializer: D) -> Result<Url, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let url = Url::deserialize(deserializer)?;
+    if url.scheme() != "http" && url.scheme() != "https" {
+        return Err(serde::de::Error::custom(format!(
+            "unsupported URL scheme `{}`, expected http or https",
+            url.scheme()
+        )));
     }
-    title_from_title_tag(dom)
-}
-
-fn get_description(dom: &VDom) -> Option<String> {
-    attr_from_first_query_match(&dom, "meta[property='og:description']", "content")
-}
-
-fn get_domain(url: &Url) -> Option<String> {
-    let host = String::from(url.host_str()?);
-    Some(host.replace("www.", ""))
-}
-
-fn get_favicon(dom: &VDom, url: &Url) -> Option<Url> {
-    let favicon = attr_from_first_query_match(dom, "link[rel='icon']", "href")?;
-    get_absolute_path(url, favicon)
-}
-
-fn get_image(dom: &VDom, url: &Url) -> Option<Url> {
-    let image = attr_from_first_query_match(dom, "meta[property='og:image']", "content")?;
-    get_absolute_path(url, image)
-}
-
-fn get_og_url(dom: &VDom) -> Option<String> {
-    attr_from_first_query_match(&dom, "meta[property='og:url']", "content")
+    Ok(url)
 }
 
-fn get_sitename(dom: &VDom) -> Option<String> {
-    attr_from_first_query_match(&dom, "meta[property='og:site_name']", "content")
-}
-
-fn get_type(dom: &VDom) -> Option<String> {
-    attr_from_first_query_match(&dom, "meta[property='og:type']", "content")
-}
-
-#[derive(Serialize, Debug)]
+#[derive(Serialize, Debug, Clone)]
 struct Response {
     title: Option<String>,
     description: Option<String>,
@@ -92,36 +60,107 @@ struct Response {
     sitename: Option<String>,
     #[serde(rename = "type")]
     site_type: Option<String>,
+    oembed: Option<OEmbed>,
 }
 
-#[get("/")]
-async fn root(params: web::Query<Params>, request: HttpRequest) -> impl Responder {
-    let user_agent = request
-        .headers()
-        .get("User-Agent")
-        .map_or(APP_USER_AGENT, |val| val.to_str().unwrap());
+fn proxy_if_enabled(state: &AppState, url: Url) -> Url {
+    if !state.proxy_media {
+        return url;
+    }
+    proxy::rewrite_to_proxy_url(&state.public_base_url, &url).unwrap_or(url)
+}
+
+struct AppState {
+    cache: PreviewCache,
+    default_cache_ttl: Duration,
+    proxy_media: bool,
+    public_base_url: String,
+    max_redirects: usize,
+    allow_private_hosts: bool,
+    max_head_bytes: usize,
+}
 
-    let reqwest_client = reqwest::Client::builder()
+fn build_fetch_client(user_agent: &str, state: &AppState) -> reqwest::Client {
+    reqwest::Client::builder()
         .user_agent(user_agent)
+        .redirect(security::redirect_policy(
+            state.max_redirects,
+            state.allow_private_hosts,
+        ))
+        .dns_resolver(Arc::new(security::SsrfGuardResolver::new(
+            state.allow_private_hosts,
+        )))
         .build()
-        .unwrap();
+        .unwrap()
+}
+
+#[get("/")]
+async fn root(
+    params: web::Query<Params>,
+    request: HttpRequest,
+    state: web::Data<AppState>,
+) -> Result<impl Responder, PreviewError> {
+    let cache_key = cache::normalize_url(&params.url);
+    if let Some(cached) = state.cache.get(&cache_key) {
+        telemetry::record_cache_hit();
+        return Ok(web::Json(cached).customize().insert_header((
+            CACHE_CONTROL,
+            format!("public, max-age={}", state.default_cache_ttl.as_secs()),
+        )));
+    }
+    telemetry::record_cache_miss();
+
+    if security::is_ip_literal_disallowed(&params.url, state.allow_private_hosts) {
+        return Err(PreviewError::DisallowedHost);
+    }
+
+    let user_agent = match request.headers().get("User-Agent") {
+        Some(header) => header.to_str().map_err(|_| PreviewError::InvalidUserAgent)?,
+        None => APP_USER_AGENT,
+    };
+
+    let reqwest_client = build_fetch_client(user_agent, &state);
 
     let client = ClientBuilder::new(reqwest_client)
         .with(TracingMiddleware::default())
         .build();
 
-    let response = client.get(params.url.clone()).send().await.unwrap();
-    let content = response.text().await.unwrap();
-    let dom = tl::parse(&content, tl::ParserOptions::default()).unwrap();
-    let title = get_title(&dom);
-    let description = get_description(&dom);
-    let domain = get_domain(&params.url);
-    let favicon = get_favicon(&dom, &params.url);
-    let image = get_image(&dom, &params.url);
-    let og_url = get_og_url(&dom);
-    let sitename = get_sitename(&dom);
-    let site_type = get_type(&dom);
-    web::Json(Response {
+    let fetch_timer = telemetry::start_fetch_timer();
+    let response = client.get(params.url.clone()).send().await?;
+    telemetry::record_fetch_status(response.status());
+    if !response.status().is_success() {
+        return Err(PreviewError::UpstreamStatus(response.status()));
+    }
+    let upstream_max_age = cache::max_age_from_headers(response.headers());
+    let headers = response.headers().clone();
+    if !decode::is_html_content_type(&headers) {
+        return Err(PreviewError::UnsupportedContentType);
+    }
+    let head_bytes = fetch::read_head(response, state.max_head_bytes).await?;
+    fetch_timer.observe(head_bytes.len() as u64);
+    let content = decode::decode(&head_bytes, &headers);
+    let dom = tl::parse(&content, tl::ParserOptions::default())
+        .map_err(|_| PreviewError::MalformedHtml)?;
+    let title = extract::get_title(&dom);
+    let description = extract::get_description(&dom);
+    let domain = extract::get_domain(&params.url);
+    let favicon = extract::get_favicon(&dom, &params.url).map(|url| proxy_if_enabled(&state, url));
+    let image = extract::get_image(&dom, &params.url).map(|url| proxy_if_enabled(&state, url));
+    let og_url = extract::get_og_url(&dom);
+    let sitename = extract::get_sitename(&dom);
+    let site_type = extract::get_type(&dom);
+    let oembed_url = extract::get_oembed_url(&dom, &params.url).filter(|oembed_url| {
+        !security::is_ip_literal_disallowed(oembed_url, state.allow_private_hosts)
+    });
+    let oembed = match oembed_url {
+        Some(oembed_url) => extract::fetch_oembed(&client, oembed_url).await,
+        None => None,
+    }
+    .map(|oembed| OEmbed {
+        thumbnail_url: oembed.thumbnail_url.map(|url| proxy_if_enabled(&state, url)),
+        ..oembed
+    });
+    let preview = Response {
         title,
         description,
         domain,
@@ -130,7 +169,17 @@ async fn root(params: web::Query<Params>, request: HttpRequest) -> impl Responde
         og_url,
         sitename,
         site_type,
-    })
+        oembed,
+    };
+
+    let ttl = upstream_max_age
+        .map(|max_age| max_age.min(state.default_cache_ttl))
+        .unwrap_or(state.default_cache_ttl);
+    state.cache.insert(cache_key, preview.clone(), ttl);
+
+    Ok(web::Json(preview)
+        .customize()
+        .insert_header((CACHE_CONTROL, format!("public, max-age={}", ttl.as_secs()))))
 }
 
 #[derive(Parser, Debug)]
@@ -140,17 +189,63 @@ struct Args {
     hostname: String,
     #[arg(long, default_value_t = 3001)]
     port: u16,
+    /// How long a fetched preview may be served from cache, in seconds.
+    #[arg(long, default_value_t = 300)]
+    cache_ttl_secs: u64,
+    /// Maximum number of preview responses kept in the cache at once.
+    #[arg(long, default_value_t = 1000)]
+    cache_capacity: usize,
+    /// Serve favicons/OG images through this service's own `/proxy` route
+    /// instead of returning the upstream URLs directly.
+    #[arg(long)]
+    proxy_media: bool,
+    /// Origin (scheme://host[:port]) this service is publicly reachable at,
+    /// used to build `--proxy-media` links. Defaults to `http://<hostname>:<port>`.
+    /// Set this explicitly when running behind a reverse proxy or TLS
+    /// terminator, since the client-supplied `Host` header is never trusted
+    /// for this (it's cached alongside the preview and would otherwise let
+    /// one request poison every other client's rewritten media links).
+    #[arg(long)]
+    public_base_url: Option<String>,
+    /// Maximum number of redirects the fetch client will follow.
+    #[arg(long, default_value_t = 5)]
+    max_redirects: usize,
+    /// Allow fetching URLs that resolve to private, loopback, or link-local
+    /// addresses. Off by default to prevent SSRF against internal hosts.
+    #[arg(long)]
+    allow_private_hosts: bool,
+    /// Hard cap, in bytes, on how much of the response body is buffered
+    /// while scanning for `</head>`.
+    #[arg(long, default_value_t = 512 * 1024)]
+    max_head_bytes: usize,
+    /// OTLP gRPC endpoint to export traces to (e.g. http://localhost:4317).
+    /// Tracing stays stdout-only when unset.
+    #[arg(long, env = "OTEL_EXPORTER_OTLP_ENDPOINT")]
+    otlp_endpoint: Option<String>,
 }
 
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
-    tracing_subscriber::fmt()
-        .with_env_filter(EnvFilter::from_default_env())
-        .with_span_events(tracing_subscriber::fmt::format::FmtSpan::CLOSE)
-        .init();
     let args = Args::parse();
+    telemetry::init_tracing(args.otlp_endpoint.as_deref());
     info!("Args: {:?}", args);
-    HttpServer::new(|| {
+
+    let public_base_url = args
+        .public_base_url
+        .clone()
+        .unwrap_or_else(|| format!("http://{}:{}", args.hostname, args.port));
+
+    let state = web::Data::new(AppState {
+        cache: PreviewCache::new(args.cache_ttl_secs, args.cache_capacity),
+        default_cache_ttl: Duration::from_secs(args.cache_ttl_secs),
+        proxy_media: args.proxy_media,
+        public_base_url,
+        max_redirects: args.max_redirects,
+        allow_private_hosts: args.allow_private_hosts,
+        max_head_bytes: args.max_head_bytes,
+    });
+
+    HttpServer::new(move || {
         let cors = Cors::default()
             .allow_any_origin()
             .allow_any_method()
@@ -159,7 +254,10 @@ async fn main() -> std::io::Result<()> {
         App::new()
             .wrap(cors)
             .wrap(TracingLogger::default())
+            .app_data(state.clone())
             .service(root)
+            .service(proxy::proxy)
+            .service(telemetry::metrics)
     })
     .bind((args.hostname, args.port))?
     .run()
@@ -0,0 +1,127 @@
+use actix_web::{
+    body::BodyStream,
+    error::{ErrorBadGateway, ErrorForbidden, ErrorPayloadTooLarge, ErrorUnsupportedMediaType},
+    get, web, Error, HttpResponse,
+};
+use futures_util::StreamExt;
+use reqwest_middleware::ClientBuilder;
+use reqwest_tracing::TracingMiddleware;
+use serde::Deserialize;
+use url::Url;
+
+use crate::{deserialize_http_url, security, AppState, APP_USER_AGENT};
+
+/// Caps the size of any single asset streamed through `/proxy`, so the
+/// endpoint can't be abused as an open relay for arbitrary large downloads.
+const MAX_PROXIED_BYTES: u64 = 10 * 1024 * 1024;
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct ProxyParams {
+    #[serde(deserialize_with = "deserialize_http_url")]
+    url: Url,
+}
+
+/// Rewrites `url` into a `{base}/proxy?url=...` link, for clients that can't
+/// follow cross-origin/mixed-content media URLs directly. `base` is the
+/// service's configured `--public-base-url`, not anything taken from the
+/// request: trusting a client-supplied `Host`/`X-Forwarded-Host` header here
+/// would let a single spoofed request poison the shared preview cache with
+/// rewritten links pointing at an attacker-controlled origin for every other
+/// client that hits the same cache key.
+pub fn rewrite_to_proxy_url(base: &str, url: &Url) -> Option<Url> {
+    let mut proxy_url = Url::parse(base).ok()?.join("/proxy").ok()?;
+    proxy_url.query_pairs_mut().append_pair("url", url.as_str());
+    Some(proxy_url)
+}
+
+#[get("/proxy")]
+pub async fn proxy(
+    params: web::Query<ProxyParams>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, Error> {
+    if security::is_ip_literal_disallowed(&params.url, state.allow_private_hosts) {
+        return Err(ErrorForbidden(
+            "refusing to fetch a private or internal address",
+        ));
+    }
+
+    let reqwest_client = crate::build_fetch_client(APP_USER_AGENT, &state);
+    let client = ClientBuilder::new(reqwest_client)
+        .with(TracingMiddleware::default())
+        .build();
+
+    let response = client
+        .get(params.url.clone())
+        .send()
+        .await
+        .map_err(ErrorBadGateway)?;
+
+    let content_type = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default()
+        .to_string();
+    if !content_type.starts_with("image/") {
+        return Err(ErrorUnsupportedMediaType(
+            "refusing to proxy a non-image content type",
+        ));
+    }
+
+    if let Some(content_length) = response.content_length() {
+        if content_length > MAX_PROXIED_BYTES {
+            return Err(ErrorPayloadTooLarge("proxied asset exceeds size limit"));
+        }
+    }
+
+    let mut streamed_bytes: u64 = 0;
+    let body = response.bytes_stream().map(move |chunk| {
+        let chunk = chunk.map_err(ErrorBadGateway)?;
+        streamed_bytes += chunk.len() as u64;
+        if streamed_bytes > MAX_PROXIED_BYTES {
+            return Err(ErrorPayloadTooLarge("proxied asset exceeds size limit"));
+        }
+        Ok(chunk)
+    });
+
+    let mut builder = HttpResponse::Ok();
+    builder
+        .content_type(content_type)
+        .insert_header((
+            reqwest::header::CACHE_CONTROL.as_str(),
+            "public, max-age=604800, immutable",
+        ));
+    if let Some(last_modified) = response
+        .headers()
+        .get(reqwest::header::LAST_MODIFIED)
+        .and_then(|value| value.to_str().ok())
+    {
+        builder.insert_header((reqwest::header::LAST_MODIFIED.as_str(), last_modified));
+    }
+
+    Ok(builder.body(BodyStream::new(body)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rewrites_onto_configured_base() {
+        let target = Url::parse("https://example.com/image.png").unwrap();
+        let rewritten = rewrite_to_proxy_url("https://preview.example", &target).unwrap();
+        assert_eq!(
+            rewritten.as_str(),
+            "https://preview.example/proxy?url=https%3A%2F%2Fexample.com%2Fimage.png"
+        );
+    }
+
+    #[test]
+    fn ignores_request_supplied_host() {
+        // Regression guard: the base must come from configuration, never
+        // from anything an untrusted client could influence.
+        let target = Url::parse("https://example.com/image.png").unwrap();
+        let rewritten = rewrite_to_proxy_url("http://trusted.example:3001", &target).unwrap();
+        assert_eq!(rewritten.host_str(), Some("trusted.example"));
+    }
+}
@@ -0,0 +1,147 @@
+use std::net::{IpAddr, Ipv6Addr, SocketAddr};
+
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use reqwest::redirect::Policy;
+use tokio::net::lookup_host;
+use url::{Host, Url};
+
+/// A [`Resolve`]r that refuses to hand back private, loopback, link-local, or
+/// unique-local addresses, so a client built with it can't be pointed at
+/// cloud metadata endpoints or the internal network by a hostname that
+/// *resolves* to one of those ranges.
+///
+/// This alone is not sufficient: `reqwest`'s connector never calls into a
+/// `Resolve` impl when the URL's host is already an IP literal (e.g.
+/// `http://169.254.169.254/`), and redirects are not re-validated at the URL
+/// level either. [`is_ip_literal_disallowed`] and [`redirect_policy`] close
+/// those two gaps.
+#[derive(Clone, Copy)]
+pub struct SsrfGuardResolver {
+    allow_private_hosts: bool,
+}
+
+impl SsrfGuardResolver {
+    pub fn new(allow_private_hosts: bool) -> Self {
+        Self { allow_private_hosts }
+    }
+}
+
+impl Resolve for SsrfGuardResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let allow_private_hosts = self.allow_private_hosts;
+        Box::pin(async move {
+            let addrs: Vec<SocketAddr> = lookup_host((name.as_str(), 0)).await?.collect();
+            let allowed: Vec<SocketAddr> = addrs
+                .into_iter()
+                .filter(|addr| allow_private_hosts || !is_disallowed_ip(&addr.ip()))
+                .collect();
+            if allowed.is_empty() {
+                return Err(Box::from(format!(
+                    "refusing to resolve `{}` to a private or internal address",
+                    name.as_str()
+                )) as Box<dyn std::error::Error + Send + Sync>);
+            }
+            Ok(Box::new(allowed.into_iter()) as Addrs)
+        })
+    }
+}
+
+/// Checks a URL's host directly against the same IP ranges the DNS resolver
+/// guard blocks, for the case where the host is already an IP literal and so
+/// never goes through [`SsrfGuardResolver::resolve`] at all.
+pub fn is_ip_literal_disallowed(url: &Url, allow_private_hosts: bool) -> bool {
+    if allow_private_hosts {
+        return false;
+    }
+    match url.host() {
+        Some(Host::Ipv4(ip)) => is_disallowed_ip(&IpAddr::V4(ip)),
+        Some(Host::Ipv6(ip)) => is_disallowed_ip(&IpAddr::V6(ip)),
+        Some(Host::Domain(_)) | None => false,
+    }
+}
+
+/// Builds a redirect policy that re-validates every redirect target's host,
+/// not just the initial request URL, and caps the hop count at
+/// `max_redirects`. This is what actually protects against a redirect to an
+/// IP-literal private address, since the DNS resolver guard never sees a
+/// host that's already an IP.
+pub fn redirect_policy(max_redirects: usize, allow_private_hosts: bool) -> Policy {
+    Policy::custom(move |attempt| {
+        // `previous()` includes the initial request URL, which isn't a hop.
+        if attempt.previous().len() > max_redirects {
+            return attempt.error("too many redirects");
+        }
+        if is_ip_literal_disallowed(attempt.url(), allow_private_hosts) {
+            return attempt.error("refusing to follow a redirect to a private or internal address");
+        }
+        attempt.follow()
+    })
+}
+
+fn is_disallowed_ip(ip: &IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(v4) => {
+            v4.is_private()
+                || v4.is_loopback()
+                || v4.is_link_local()
+                || v4.is_unspecified()
+                || v4.is_broadcast()
+                || v4.is_documentation()
+        }
+        IpAddr::V6(v6) => {
+            v6.is_loopback() || v6.is_unspecified() || is_unique_local(v6) || is_link_local(v6)
+        }
+    }
+}
+
+/// `Ipv6Addr::is_unique_local` is still unstable, so replicate the fc00::/7 check.
+fn is_unique_local(v6: &Ipv6Addr) -> bool {
+    (v6.segments()[0] & 0xfe00) == 0xfc00
+}
+
+/// `Ipv6Addr::is_unicast_link_local` is still unstable, so replicate the fe80::/10 check.
+fn is_link_local(v6: &Ipv6Addr) -> bool {
+    (v6.segments()[0] & 0xffc0) == 0xfe80
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn blocks_link_local_v4_metadata_endpoint() {
+        assert!(is_disallowed_ip(&"169.254.169.254".parse().unwrap()));
+    }
+
+    #[test]
+    fn blocks_loopback_and_private_v4_ranges() {
+        assert!(is_disallowed_ip(&"127.0.0.1".parse().unwrap()));
+        assert!(is_disallowed_ip(&"10.0.0.5".parse().unwrap()));
+        assert!(is_disallowed_ip(&"192.168.1.1".parse().unwrap()));
+    }
+
+    #[test]
+    fn allows_public_v4() {
+        assert!(!is_disallowed_ip(&"93.184.216.34".parse().unwrap()));
+    }
+
+    #[test]
+    fn blocks_loopback_and_unique_local_v6() {
+        assert!(is_disallowed_ip(&"::1".parse().unwrap()));
+        assert!(is_disallowed_ip(&"fc00::1".parse().unwrap()));
+        assert!(is_disallowed_ip(&"fe80::1".parse().unwrap()));
+    }
+
+    #[test]
+    fn ip_literal_url_is_checked_directly() {
+        let url = Url::parse("http://169.254.169.254/").unwrap();
+        assert!(is_ip_literal_disallowed(&url, false));
+        assert!(!is_ip_literal_disallowed(&url, true));
+    }
+
+    #[test]
+    fn domain_host_is_not_checked_here() {
+        let url = Url::parse("http://169.254.169.254.example.com/").unwrap();
+        assert!(!is_ip_literal_disallowed(&url, false));
+    }
+}
@@ -0,0 +1,125 @@
+use std::sync::OnceLock;
+
+use actix_web::{get, HttpResponse, Responder};
+use opentelemetry::global;
+use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_sdk::{propagation::TraceContextPropagator, trace, Resource};
+use prometheus::{
+    register_histogram, register_int_counter, register_int_counter_vec, Encoder, Histogram,
+    IntCounter, IntCounterVec, TextEncoder,
+};
+use tracing_subscriber::{fmt, layer::SubscriberExt, util::SubscriberInitExt, EnvFilter};
+
+/// Installs the `tracing` subscriber: an `EnvFilter`-gated stdout formatter
+/// as before, plus, when `otlp_endpoint` is set, a batch OTLP exporter so
+/// spans from the outbound `reqwest` calls and the actix request flow are
+/// shipped to a collector.
+pub fn init_tracing(otlp_endpoint: Option<&str>) {
+    let otel_layer = otlp_endpoint.map(|endpoint| {
+        global::set_text_map_propagator(TraceContextPropagator::new());
+        let tracer = opentelemetry_otlp::new_pipeline()
+            .tracing()
+            .with_exporter(
+                opentelemetry_otlp::new_exporter()
+                    .tonic()
+                    .with_endpoint(endpoint),
+            )
+            .with_trace_config(trace::config().with_resource(Resource::new(vec![
+                opentelemetry::KeyValue::new("service.name", "link-preview-rs"),
+            ])))
+            .install_batch(opentelemetry_sdk::runtime::Tokio)
+            .expect("failed to install OTLP tracer");
+        tracing_opentelemetry::layer().with_tracer(tracer)
+    });
+
+    tracing_subscriber::registry()
+        .with(EnvFilter::from_default_env())
+        .with(fmt::layer().with_span_events(fmt::format::FmtSpan::CLOSE))
+        .with(otel_layer)
+        .init();
+}
+
+fn fetches_total() -> &'static IntCounterVec {
+    static METRIC: OnceLock<IntCounterVec> = OnceLock::new();
+    METRIC.get_or_init(|| {
+        register_int_counter_vec!(
+            "link_preview_fetches_total",
+            "Upstream fetches, labeled by HTTP response status class",
+            &["status_class"]
+        )
+        .unwrap()
+    })
+}
+
+fn fetch_duration_seconds() -> &'static Histogram {
+    static METRIC: OnceLock<Histogram> = OnceLock::new();
+    METRIC.get_or_init(|| {
+        register_histogram!(
+            "link_preview_fetch_duration_seconds",
+            "Time spent fetching and reading an upstream response"
+        )
+        .unwrap()
+    })
+}
+
+fn bytes_downloaded_total() -> &'static IntCounter {
+    static METRIC: OnceLock<IntCounter> = OnceLock::new();
+    METRIC.get_or_init(|| {
+        register_int_counter!(
+            "link_preview_bytes_downloaded_total",
+            "Total bytes read from upstream responses"
+        )
+        .unwrap()
+    })
+}
+
+fn cache_results_total() -> &'static IntCounterVec {
+    static METRIC: OnceLock<IntCounterVec> = OnceLock::new();
+    METRIC.get_or_init(|| {
+        register_int_counter_vec!(
+            "link_preview_cache_results_total",
+            "Preview cache lookups, labeled by hit/miss",
+            &["result"]
+        )
+        .unwrap()
+    })
+}
+
+pub fn record_cache_hit() {
+    cache_results_total().with_label_values(&["hit"]).inc();
+}
+
+pub fn record_cache_miss() {
+    cache_results_total().with_label_values(&["miss"]).inc();
+}
+
+pub fn record_fetch_status(status: reqwest::StatusCode) {
+    let status_class = format!("{}xx", status.as_u16() / 100);
+    fetches_total().with_label_values(&[&status_class]).inc();
+}
+
+/// Starts a timer for an in-flight fetch; call [`FetchTimer::observe`] once
+/// reading the response body has stopped.
+pub struct FetchTimer(std::time::Instant);
+
+pub fn start_fetch_timer() -> FetchTimer {
+    FetchTimer(std::time::Instant::now())
+}
+
+impl FetchTimer {
+    pub fn observe(self, bytes_downloaded: u64) {
+        fetch_duration_seconds().observe(self.0.elapsed().as_secs_f64());
+        bytes_downloaded_total().inc_by(bytes_downloaded);
+    }
+}
+
+#[get("/metrics")]
+pub async fn metrics() -> impl Responder {
+    let metric_families = prometheus::gather();
+    let encoder = TextEncoder::new();
+    let mut buffer = Vec::new();
+    encoder.encode(&metric_families, &mut buffer).unwrap();
+    HttpResponse::Ok()
+        .content_type(encoder.format_type())
+        .body(buffer)
+}